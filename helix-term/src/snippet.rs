@@ -0,0 +1,636 @@
+//! Parsing and rendering of the LSP snippet grammar used by
+//! `CompletionItem::insert_text`/`text_edit.new_text` when
+//! `insert_text_format == Some(InsertTextFormat::SNIPPET)`.
+//!
+//! See the LSP specification's "Snippet Syntax" section for the grammar this
+//! mirrors: tabstops (`$1`, `${1}`), placeholders with defaults
+//! (`${1:default}`), choices (`${1|a,b,c|}`) and variables
+//! (`$TM_SELECTED_TEXT`, `${TM_SELECTED_TEXT:default}`).
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A single element of a parsed snippet body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetElement {
+    Text(String),
+    Tabstop {
+        index: usize,
+    },
+    Placeholder {
+        index: usize,
+        value: Vec<SnippetElement>,
+    },
+    Choice {
+        index: usize,
+        choices: Vec<String>,
+    },
+    Variable {
+        name: String,
+        default: Vec<SnippetElement>,
+    },
+}
+
+/// A parsed snippet body, ready to be rendered into concrete text.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Snippet {
+    elements: Vec<SnippetElement>,
+}
+
+/// The result of rendering a [`Snippet`]: the substituted text, plus every
+/// tabstop's char range within that text.
+#[derive(Debug, Clone, Default)]
+pub struct RenderedSnippet {
+    pub text: String,
+    /// Tabstop ranges grouped by tabstop index, ordered the way a user tabs
+    /// through them: ascending by index, with `$0` (or an implicit
+    /// end-of-snippet stop when none was specified) last. A group holds more
+    /// than one range when the same index occurs multiple times in the
+    /// snippet (mirrored placeholders).
+    pub tabstops: Vec<Vec<(usize, usize)>>,
+}
+
+impl RenderedSnippet {
+    /// Strips a literal prefix already present in the buffer (e.g. the part
+    /// of an identifier typed before the completion was accepted) from the
+    /// front of the rendered text, shifting tabstop ranges to match.
+    pub fn strip_prefix(&mut self, prefix: &str) {
+        if prefix.is_empty() || !self.text.starts_with(prefix) {
+            return;
+        }
+        let removed = prefix.chars().count();
+        self.text.drain(..prefix.len());
+        for group in &mut self.tabstops {
+            for (start, end) in group.iter_mut() {
+                *start = start.saturating_sub(removed);
+                *end = end.saturating_sub(removed);
+            }
+        }
+    }
+}
+
+impl Snippet {
+    pub fn parse(source: &str) -> Self {
+        Self {
+            elements: Parser::new(source).parse(),
+        }
+    }
+
+    /// Renders the snippet, substituting variables via `resolve_var` (called
+    /// with the variable name, e.g. `TM_SELECTED_TEXT`) and placeholders with
+    /// their defaults (choices use their first option as the default).
+    pub fn render(&self, resolve_var: &impl Fn(&str) -> Option<String>) -> RenderedSnippet {
+        let mut text = String::new();
+        let mut groups: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+        render_elements(&self.elements, &mut text, &mut groups, resolve_var);
+
+        let end = text.chars().count();
+        let final_stop = groups.remove(&0).unwrap_or_else(|| vec![(end, end)]);
+        let mut tabstops: Vec<_> = groups.into_values().collect();
+        tabstops.push(final_stop);
+
+        RenderedSnippet { text, tabstops }
+    }
+}
+
+fn render_elements(
+    elements: &[SnippetElement],
+    text: &mut String,
+    groups: &mut BTreeMap<usize, Vec<(usize, usize)>>,
+    resolve_var: &impl Fn(&str) -> Option<String>,
+) {
+    for element in elements {
+        match element {
+            SnippetElement::Text(s) => text.push_str(s),
+            SnippetElement::Tabstop { index } => {
+                let at = text.chars().count();
+                groups.entry(*index).or_default().push((at, at));
+            }
+            SnippetElement::Placeholder { index, value } => {
+                let start = text.chars().count();
+                render_elements(value, text, groups, resolve_var);
+                let end = text.chars().count();
+                groups.entry(*index).or_default().push((start, end));
+            }
+            SnippetElement::Choice { index, choices } => {
+                let start = text.chars().count();
+                text.push_str(choices.first().map(String::as_str).unwrap_or_default());
+                let end = text.chars().count();
+                groups.entry(*index).or_default().push((start, end));
+            }
+            SnippetElement::Variable { name, default } => match resolve_var(name) {
+                Some(value) => text.push_str(&value),
+                None => render_elements(default, text, groups, resolve_var),
+            },
+        }
+    }
+}
+
+/// Hand-written recursive-descent parser over the LSP snippet grammar.
+/// Malformed input never produces an error, since a slightly-wrong snippet
+/// should still be usable: a `$` that doesn't start a valid construct is kept
+/// as a literal `$` (whatever of the construct was already consumed is
+/// dropped), and a `${N:` or `${VAR:` body with no closing `}` runs to the
+/// end of the input.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Vec<SnippetElement> {
+        self.parse_until(None)
+    }
+
+    /// Parses elements until EOF, or, when `closing` is `Some`, until that
+    /// exact character is consumed (used for the body of a `${...}` group).
+    fn parse_until(&mut self, closing: Option<char>) -> Vec<SnippetElement> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if Some(c) == closing {
+                break;
+            }
+            match c {
+                '\\' => {
+                    self.chars.next();
+                    match self.chars.next() {
+                        Some(escaped @ ('$' | '}' | '\\')) => text.push(escaped),
+                        Some(other) => {
+                            text.push('\\');
+                            text.push(other);
+                        }
+                        None => text.push('\\'),
+                    }
+                }
+                '$' => {
+                    self.chars.next();
+                    match self.parse_dollar() {
+                        Some(element) => {
+                            if !text.is_empty() {
+                                elements.push(SnippetElement::Text(std::mem::take(&mut text)));
+                            }
+                            elements.push(element);
+                        }
+                        None => text.push('$'),
+                    }
+                }
+                _ => {
+                    self.chars.next();
+                    text.push(c);
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            elements.push(SnippetElement::Text(text));
+        }
+        elements
+    }
+
+    /// Parses everything after a `$` has already been consumed.
+    fn parse_dollar(&mut self) -> Option<SnippetElement> {
+        match self.chars.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let index = self.parse_int();
+                Some(SnippetElement::Tabstop { index })
+            }
+            Some(&c) if is_variable_start(c) => {
+                let name = self.parse_ident();
+                Some(SnippetElement::Variable {
+                    name,
+                    default: Vec::new(),
+                })
+            }
+            Some('{') => {
+                self.chars.next();
+                self.parse_braced()
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the body of a `${...}` construct; the opening `{` has already
+    /// been consumed.
+    fn parse_braced(&mut self) -> Option<SnippetElement> {
+        match self.chars.peek().copied() {
+            Some(c) if c.is_ascii_digit() => {
+                let index = self.parse_int();
+                match self.chars.peek() {
+                    Some('}') => {
+                        self.chars.next();
+                        Some(SnippetElement::Tabstop { index })
+                    }
+                    Some(':') => {
+                        self.chars.next();
+                        let value = self.parse_until(Some('}'));
+                        self.chars.next(); // consume '}'
+                        Some(SnippetElement::Placeholder { index, value })
+                    }
+                    Some('|') => {
+                        self.chars.next();
+                        let choices = self.parse_choices();
+                        Some(SnippetElement::Choice { index, choices })
+                    }
+                    _ => None,
+                }
+            }
+            Some(c) if is_variable_start(c) => {
+                let name = self.parse_ident();
+                match self.chars.peek() {
+                    Some('}') => {
+                        self.chars.next();
+                        Some(SnippetElement::Variable {
+                            name,
+                            default: Vec::new(),
+                        })
+                    }
+                    Some(':') => {
+                        self.chars.next();
+                        let default = self.parse_until(Some('}'));
+                        self.chars.next(); // consume '}'
+                        Some(SnippetElement::Variable { name, default })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the comma-separated options of a `${N|a,b,c|}` choice; the
+    /// leading `|` has already been consumed. Consumes the trailing `|}`.
+    fn parse_choices(&mut self) -> Vec<String> {
+        let mut choices = Vec::new();
+        let mut current = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\\') => {
+                    if let Some(c) = self.chars.next() {
+                        current.push(c);
+                    }
+                }
+                Some(',') => choices.push(std::mem::take(&mut current)),
+                Some('|') if self.chars.peek() == Some(&'}') => {
+                    self.chars.next();
+                    break;
+                }
+                Some(c) => current.push(c),
+                None => break,
+            }
+        }
+        choices.push(current);
+        choices
+    }
+
+    fn parse_int(&mut self) -> usize {
+        let mut digits = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        digits.parse().unwrap_or(0)
+    }
+
+    fn parse_ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                ident.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        ident
+    }
+}
+
+fn is_variable_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+/// Tracks the tabstops produced by rendering a snippet into the document,
+/// and which one the user is currently sitting on.
+#[derive(Debug, Clone)]
+pub struct ActiveSnippet {
+    tabstops: Vec<Vec<(usize, usize)>>,
+    current: usize,
+}
+
+impl ActiveSnippet {
+    /// Builds the navigable state for a rendered snippet's tabstops (already
+    /// shifted to absolute positions in the document). Returns `None` when
+    /// there's nothing to navigate, i.e. only the final stop (an explicit `$0`
+    /// or the implicit end-of-snippet stop) exists.
+    pub fn new(tabstops: Vec<Vec<(usize, usize)>>) -> Option<Self> {
+        if tabstops.len() <= 1 {
+            return None;
+        }
+        Some(Self {
+            tabstops,
+            current: 0,
+        })
+    }
+
+    pub fn current_ranges(&self) -> &[(usize, usize)] {
+        &self.tabstops[self.current]
+    }
+
+    pub fn goto_next(&mut self) -> bool {
+        if self.current + 1 < self.tabstops.len() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn goto_prev(&mut self) -> bool {
+        if self.current > 0 {
+            self.current -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(source: &str) -> RenderedSnippet {
+        Snippet::parse(source).render(&|_| None)
+    }
+
+    #[test]
+    fn parses_plain_text() {
+        let snippet = Snippet::parse("hello world");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Text("hello world".to_string())]
+        );
+    }
+
+    #[test]
+    fn parses_tabstops() {
+        let snippet = Snippet::parse("foo($1, $2)$0");
+        assert_eq!(
+            snippet.elements,
+            vec![
+                SnippetElement::Text("foo(".to_string()),
+                SnippetElement::Tabstop { index: 1 },
+                SnippetElement::Text(", ".to_string()),
+                SnippetElement::Tabstop { index: 2 },
+                SnippetElement::Text(")".to_string()),
+                SnippetElement::Tabstop { index: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_braced_tabstop() {
+        let snippet = Snippet::parse("${1}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Tabstop { index: 1 }]
+        );
+    }
+
+    #[test]
+    fn parses_placeholder_with_default() {
+        let snippet = Snippet::parse("${1:foo}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Placeholder {
+                index: 1,
+                value: vec![SnippetElement::Text("foo".to_string())],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_nested_placeholder() {
+        let snippet = Snippet::parse("${1:foo($2)}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Placeholder {
+                index: 1,
+                value: vec![
+                    SnippetElement::Text("foo(".to_string()),
+                    SnippetElement::Tabstop { index: 2 },
+                    SnippetElement::Text(")".to_string()),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_choice() {
+        let snippet = Snippet::parse("${1|foo,bar,baz|}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Choice {
+                index: 1,
+                choices: vec!["foo".to_string(), "bar".to_string(), "baz".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_choice_with_escaped_comma_and_pipe() {
+        let snippet = Snippet::parse(r"${1|a\,b,c\|d|}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Choice {
+                index: 1,
+                choices: vec!["a,b".to_string(), "c|d".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_variable() {
+        let snippet = Snippet::parse("$TM_FILENAME");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Variable {
+                name: "TM_FILENAME".to_string(),
+                default: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_variable_with_default() {
+        let snippet = Snippet::parse("${TM_SELECTED_TEXT:fallback}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Variable {
+                name: "TM_SELECTED_TEXT".to_string(),
+                default: vec![SnippetElement::Text("fallback".to_string())],
+            }]
+        );
+    }
+
+    #[test]
+    fn escapes_dollar_brace_and_backslash() {
+        let snippet = Snippet::parse(r"\$1 \{ \\ \}");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Text(r"$1 \{ \ }".to_string())]
+        );
+    }
+
+    #[test]
+    fn unrecognized_escape_keeps_backslash() {
+        let snippet = Snippet::parse(r"\n");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Text(r"\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn malformed_unterminated_braced_construct_degrades_to_text() {
+        // no `:`, `|` or closing `}` after the index - the parser shouldn't
+        // panic, it just falls back to treating the `$` as literal text
+        // (the already-consumed `{1` is lost, which is an acceptable
+        // tradeoff for a slightly-malformed snippet).
+        let snippet = Snippet::parse("foo ${1");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Text("foo $".to_string())]
+        );
+    }
+
+    #[test]
+    fn malformed_unterminated_placeholder_body_is_absorbed() {
+        // a `${1:...` with no closing `}` still parses as a placeholder -
+        // `parse_until` just runs to end-of-input for the value instead of
+        // stopping at `}`. Still no panic, which is what matters here.
+        let snippet = Snippet::parse("foo ${1:bar");
+        assert_eq!(
+            snippet.elements,
+            vec![
+                SnippetElement::Text("foo ".to_string()),
+                SnippetElement::Placeholder {
+                    index: 1,
+                    value: vec![SnippetElement::Text("bar".to_string())],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_dollar_with_nothing_after_is_literal() {
+        let snippet = Snippet::parse("cost: $");
+        assert_eq!(
+            snippet.elements,
+            vec![SnippetElement::Text("cost: $".to_string())]
+        );
+    }
+
+    #[test]
+    fn renders_plain_text() {
+        let rendered = render("hello world");
+        assert_eq!(rendered.text, "hello world");
+    }
+
+    #[test]
+    fn renders_mirrored_tabstops_into_one_group() {
+        // `$1` appearing twice should land in the same tabstop group so
+        // navigating to it selects both occurrences at once.
+        let rendered = render("<$1>...</$1>");
+        assert_eq!(rendered.text, "<>...</>");
+        assert_eq!(rendered.tabstops.len(), 2); // group 1, plus the implicit $0
+        let mirrored = &rendered.tabstops[0];
+        assert_eq!(mirrored, &vec![(1, 1), (7, 7)]);
+    }
+
+    #[test]
+    fn renders_final_stop_last_even_out_of_order() {
+        let rendered = render("$2 $0 $1");
+        // final stop ($0) must be last regardless of source order or index value
+        let final_group = rendered.tabstops.last().unwrap();
+        assert_eq!(final_group, &vec![(1, 1)]);
+    }
+
+    #[test]
+    fn renders_implicit_final_stop_at_end_when_no_dollar_zero() {
+        let rendered = render("foo$1bar");
+        let final_group = rendered.tabstops.last().unwrap();
+        let final_end = "foo".len() + "bar".len();
+        assert_eq!(final_group, &vec![(final_end, final_end)]);
+    }
+
+    #[test]
+    fn renders_choice_using_first_option() {
+        let rendered = render("${1|alpha,beta|}");
+        assert_eq!(rendered.text, "alpha");
+    }
+
+    #[test]
+    fn renders_variable_using_resolver_or_default() {
+        let resolved = Snippet::parse("$TM_FILENAME").render(&|name| {
+            (name == "TM_FILENAME").then(|| "main.rs".to_string())
+        });
+        assert_eq!(resolved.text, "main.rs");
+
+        let unresolved =
+            Snippet::parse("${TM_FILENAME:untitled}").render(&|_| None);
+        assert_eq!(unresolved.text, "untitled");
+    }
+
+    #[test]
+    fn strip_prefix_shifts_tabstops() {
+        let mut rendered = render("foo$1bar");
+        rendered.strip_prefix("fo");
+        assert_eq!(rendered.text, "obar");
+        // the tabstop was at (3, 3) before stripping 2 chars off the front
+        let tabstop_group = &rendered.tabstops[0];
+        assert_eq!(tabstop_group, &vec![(1, 1)]);
+    }
+
+    #[test]
+    fn strip_prefix_is_noop_when_not_a_prefix() {
+        let mut rendered = render("foo$1bar");
+        let before = rendered.text.clone();
+        rendered.strip_prefix("xyz");
+        assert_eq!(rendered.text, before);
+    }
+
+    #[test]
+    fn active_snippet_new_returns_none_for_only_the_final_stop() {
+        assert!(ActiveSnippet::new(vec![vec![(0, 0)]]).is_none());
+    }
+
+    #[test]
+    fn active_snippet_cycles_through_tabstops() {
+        let mut active = ActiveSnippet::new(vec![vec![(0, 0)], vec![(5, 5)], vec![(8, 8)]]).unwrap();
+        assert_eq!(active.current_ranges(), &[(0, 0)]);
+
+        assert!(active.goto_next());
+        assert_eq!(active.current_ranges(), &[(5, 5)]);
+        assert!(active.goto_next());
+        assert_eq!(active.current_ranges(), &[(8, 8)]);
+        assert!(!active.goto_next(), "already on the last tabstop");
+
+        assert!(active.goto_prev());
+        assert_eq!(active.current_ranges(), &[(5, 5)]);
+        assert!(active.goto_prev());
+        assert!(!active.goto_prev(), "already on the first tabstop");
+    }
+}
@@ -1,22 +1,28 @@
 use crate::compositor::{Component, Context, Event, EventResult};
 use helix_core::regex::Regex;
-use helix_view::{apply_transaction, editor::CompleteAction, ViewId};
+use helix_view::{
+    apply_transaction,
+    editor::{CompleteAction, CompletionDocsConfig, CompletionDocsSide},
+    ViewId,
+};
 use once_cell::sync::Lazy;
 use tui::buffer::Buffer as Surface;
-use tui::text::Spans;
+use tui::text::{Span, Spans};
 
 use std::borrow::Cow;
 use std::fs::Permissions;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use helix_core::{Change, Transaction};
+use helix_core::{chars::char_is_word, Change, Range, Selection, Transaction};
 use helix_view::{
     graphics::Rect,
     input::{KeyCode, KeyEvent},
+    theme::Theme,
     Document, Editor,
 };
 
 use crate::commands;
+use crate::snippet::{ActiveSnippet, Snippet};
 use crate::ui::{menu, Markdown, Menu, Popup, PromptEvent};
 
 use helix_lsp::{lsp, util, OffsetEncoding};
@@ -33,6 +39,85 @@ pub enum PathType {
     Unknown,
 }
 
+impl PathType {
+    fn glyph(&self) -> &'static str {
+        match self {
+            PathType::Dir => "folder",
+            PathType::File => "file",
+            PathType::Symlink => "symlink",
+            PathType::Unknown => "unknown",
+        }
+    }
+
+    /// Classifies a path on disk the same way completion items are tagged,
+    /// used when listing a directory's children for the preview popup.
+    fn of(path: &Path) -> Self {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_symlink() => PathType::Symlink,
+            Ok(metadata) if metadata.is_dir() => PathType::Dir,
+            Ok(_) => PathType::File,
+            Err(_) => PathType::Unknown,
+        }
+    }
+}
+
+/// Width the space right of the completion menu must exceed for
+/// [`CompletionDocsSide::Auto`] to place the documentation popup there;
+/// otherwise it goes below. The left side is not considered.
+const MIN_SIDE_WIDTH: u16 = 30;
+
+/// Any execute bit set, mirroring how `ls --color`/exa flag executables.
+fn is_executable(permissions: &Permissions) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// A cached preview of a `CompletionItem::Path`'s target, shown in the docs
+/// popup alongside the completion menu.
+#[derive(Debug, Clone)]
+enum PathPreview {
+    File { language: String, contents: String },
+    Dir { children: Vec<(String, PathType)> },
+    Symlink { target: PathBuf },
+}
+
+impl PathPreview {
+    /// Renders the preview as markdown, clipped to `max_lines` so a huge file
+    /// or directory doesn't blow past the popup area.
+    fn to_markdown(&self, max_lines: usize) -> String {
+        match self {
+            PathPreview::File { language, contents } => {
+                let body: String = contents.lines().take(max_lines).collect::<Vec<_>>().join("\n");
+                format!("```{}\n{}\n```", language, body)
+            }
+            PathPreview::Dir { children } => children
+                .iter()
+                .take(max_lines)
+                .map(|(name, path_type)| format!("{} {}", path_type.glyph(), name))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            PathPreview::Symlink { target } => format!("-> {}", target.display()),
+        }
+    }
+}
+
+/// Upper bound on how many bytes of a previewed file we read into memory.
+const PATH_PREVIEW_MAX_BYTES: usize = 8 * 1024;
+
+/// Upper bound on how many lines of a preview (file contents or directory
+/// listing) we hand to `Markdown`. Deliberately generous rather than tied to
+/// the popup's rendered height: the docs popup scrolls, so clipping to
+/// `area.height` at render time would just hide content the user could
+/// otherwise page down to.
+const PATH_PREVIEW_MAX_LINES: usize = 500;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CompletionItem {
     LSP {
@@ -48,7 +133,7 @@ pub enum CompletionItem {
 }
 
 impl menu::Item for CompletionItem {
-    type Data = ();
+    type Data = Theme;
     fn sort_text(&self, data: &Self::Data) -> Cow<str> {
         self.filter_text(data)
     }
@@ -119,15 +204,29 @@ impl menu::Item for CompletionItem {
                     }
                     None => "",
                 }),
-                CompletionItem::Path { path_type, .. } => menu::Cell::from({
-                    // TODO probably check permissions/or (coloring maybe)
-                    match path_type {
-                        PathType::Dir => "folder",
-                        PathType::File => "file",
-                        PathType::Symlink => "symlink",
-                        PathType::Unknown => "unknown",
+                CompletionItem::Path {
+                    path_type,
+                    permissions,
+                    ..
+                } => {
+                    let scope = match path_type {
+                        PathType::Dir => Some("ui.completion.directory"),
+                        PathType::Symlink => Some("ui.completion.symlink"),
+                        PathType::File if is_executable(permissions) => {
+                            Some("ui.completion.executable")
+                        }
+                        PathType::File | PathType::Unknown => None,
+                    };
+                    match scope {
+                        Some(scope) => {
+                            menu::Cell::from(Spans::from(Span::styled(
+                                path_type.glyph(),
+                                data.get(scope),
+                            )))
+                        }
+                        None => menu::Cell::from(path_type.glyph()),
                     }
-                }),
+                }
             },
         ])
     }
@@ -140,6 +239,14 @@ pub struct Completion {
     #[allow(dead_code)]
     trigger_offset: usize,
     // TODO: maintain a completioncontext with trigger kind & trigger char
+    /// Cache of the last `CompletionItem::Path` preview that was rendered, so
+    /// re-rendering the same selection doesn't re-hit the filesystem.
+    path_preview_cache: Option<(PathBuf, Option<PathPreview>)>,
+    /// Vertical scroll offset, in rendered lines, of the documentation popup.
+    /// Reset to the top whenever the menu is re-filtered (see
+    /// `recompute_filter`); moving the selection within the menu keeps the
+    /// offset, which `render` clamps to the newly selected item's content.
+    doc_scroll: u16,
 }
 
 impl Completion {
@@ -158,14 +265,38 @@ impl Completion {
         });
 
         // Then create the menu
-        let menu = Menu::new(items, (), move |editor: &mut Editor, item, event| {
+        let menu = Menu::new(items, editor.theme.clone(), move |editor: &mut Editor, item, event| {
+            /// Resolves the LSP snippet variables we currently support.
+            fn resolve_snippet_variable(doc: &Document, view_id: ViewId, name: &str) -> Option<String> {
+                let text = doc.text().slice(..);
+                match name {
+                    "TM_SELECTED_TEXT" => {
+                        let range = doc.selection(view_id).primary();
+                        (!range.is_empty()).then(|| Cow::from(range.fragment(text)).into_owned())
+                    }
+                    "TM_CURRENT_LINE" => {
+                        let cursor = doc.selection(view_id).primary().cursor(text);
+                        Some(text.line(text.char_to_line(cursor)).to_string())
+                    }
+                    "TM_FILENAME" => doc
+                        .path()
+                        .and_then(|path| path.file_name())
+                        .map(|name| name.to_string_lossy().into_owned()),
+                    _ => None,
+                }
+            }
+
+            /// Builds the transaction that inserts `item`'s text (expanding
+            /// it as a snippet when `insert_text_format` calls for it), and
+            /// the navigable tabstop state for that snippet, if any.
             fn item_to_transaction(
                 doc: &Document,
                 view_id: ViewId,
                 item: &CompletionItem,
                 start_offset: usize,
                 trigger_offset: usize,
-            ) -> Transaction {
+                completion_replace: bool,
+            ) -> (Transaction, Option<ActiveSnippet>) {
                 // for now only LSP support
                 match item {
                     CompletionItem::LSP {
@@ -173,27 +304,85 @@ impl Completion {
                         offset_encoding,
                         ..
                     } => {
-                        let transaction = if let Some(edit) = &item.text_edit {
-                            let edit = match edit {
+                        let is_snippet = item.insert_text_format == Some(lsp::InsertTextFormat::SNIPPET);
+
+                        if let Some(edit) = &item.text_edit {
+                            let mut edit = match edit {
                                 lsp::CompletionTextEdit::Edit(edit) => edit.clone(),
                                 lsp::CompletionTextEdit::InsertAndReplace(item) => {
-                                    // TODO: support using "insert" instead of "replace" via user config
-                                    lsp::TextEdit::new(item.replace, item.new_text.clone())
+                                    // `completion-replace` picks which of the server-provided
+                                    // ranges we use: the wider `replace` range (overwriting the
+                                    // rest of the word under the cursor) or the narrower
+                                    // `insert` range (leaving trailing characters untouched).
+                                    let range = if completion_replace {
+                                        item.replace
+                                    } else {
+                                        item.insert
+                                    };
+                                    lsp::TextEdit::new(range, item.new_text.clone())
                                 }
                             };
 
-                            util::generate_transaction_from_completion_edit(
+                            let rendered = is_snippet.then(|| {
+                                let snippet = Snippet::parse(&edit.new_text);
+                                snippet.render(&|name| resolve_snippet_variable(doc, view_id, name))
+                            });
+                            if let Some(rendered) = &rendered {
+                                edit.new_text = rendered.text.clone();
+                            }
+
+                            let transaction = util::generate_transaction_from_completion_edit(
                                 doc.text(),
                                 doc.selection(view_id),
                                 edit,
                                 *offset_encoding, // TODO: should probably transcode in Client
-                            )
+                            );
+
+                            let snippet = rendered.and_then(|rendered| {
+                                let edit_start = transaction
+                                    .changes_iter()
+                                    .find(|(start, end, _)| (*start..=*end).contains(&trigger_offset))
+                                    .map_or(trigger_offset, |(start, ..)| start);
+
+                                let tabstops = rendered
+                                    .tabstops
+                                    .into_iter()
+                                    .map(|group| {
+                                        group
+                                            .into_iter()
+                                            .map(|(s, e)| (s + edit_start, e + edit_start))
+                                            .collect()
+                                    })
+                                    .collect();
+                                ActiveSnippet::new(tabstops)
+                            });
+
+                            (transaction, snippet)
                         } else {
                             let text = item.insert_text.as_ref().unwrap_or(&item.label);
                             // Some LSPs just give you an insertText with no offset ¯\_(ツ)_/¯
-                            // in these cases we need to check for a common prefix and remove it
+                            // in these cases we need to check for a common prefix and remove it.
+                            // There's no separate insert/replace range to pick between here, so
+                            // `completion_replace` instead decides whether the word characters
+                            // trailing the cursor get overwritten too (see `replace_end` below).
                             let prefix = Cow::from(doc.text().slice(start_offset..trigger_offset));
-                            let text = text.trim_start_matches::<&str>(&prefix);
+
+                            /// With `completion_replace` enabled, extends the edit past `cursor`
+                            /// to cover the rest of the word under it, mirroring the `replace`
+                            /// range the `text_edit` path above picks when the config is enabled.
+                            /// Without it, the edit stops at `cursor`, leaving trailing characters
+                            /// untouched, same as plain insertion.
+                            fn replace_end(doc: &Document, cursor: usize, completion_replace: bool) -> usize {
+                                if !completion_replace {
+                                    return cursor;
+                                }
+                                let text = doc.text().slice(..);
+                                let mut end = cursor;
+                                while end < text.len_chars() && char_is_word(text.char(end)) {
+                                    end += 1;
+                                }
+                                end
+                            }
 
                             // TODO: this needs to be true for the numbers to work out correctly
                             // in the closure below. It's passed in to a callback as this same
@@ -206,18 +395,47 @@ impl Completion {
                                     == trigger_offset
                             );
 
-                            Transaction::change_by_selection(
+                            let (text, snippet) = if is_snippet {
+                                let snippet = Snippet::parse(text);
+                                let mut rendered = snippet
+                                    .render(&|name| resolve_snippet_variable(doc, view_id, name));
+                                rendered.strip_prefix(&prefix);
+                                (rendered.text, Some(rendered.tabstops))
+                            } else {
+                                (
+                                    text.trim_start_matches::<&str>(&prefix).to_string(),
+                                    None,
+                                )
+                            };
+
+                            let transaction = Transaction::change_by_selection(
                                 doc.text(),
                                 doc.selection(view_id),
                                 |range| {
                                     let cursor = range.cursor(doc.text().slice(..));
+                                    let end = replace_end(doc, cursor, completion_replace);
 
-                                    (cursor, cursor, Some(text.into()))
+                                    (cursor, end, Some(text.as_str().into()))
                                 },
-                            )
-                        };
+                            );
 
-                        transaction
+                            // the debug_assert above guarantees the cursor we just
+                            // inserted at is `trigger_offset`.
+                            let snippet = snippet.and_then(|tabstops| {
+                                let tabstops = tabstops
+                                    .into_iter()
+                                    .map(|group| {
+                                        group
+                                            .into_iter()
+                                            .map(|(s, e)| (s + trigger_offset, e + trigger_offset))
+                                            .collect()
+                                    })
+                                    .collect();
+                                ActiveSnippet::new(tabstops)
+                            });
+
+                            (transaction, snippet)
+                        }
                     }
                     CompletionItem::Path { path, .. } => {
                         let text = doc.text().slice(..);
@@ -238,7 +456,7 @@ impl Completion {
                             prefix += "/";
                         }
                         let text = path_head.trim_start_matches::<&str>(&prefix);
-                        Transaction::change_by_selection(
+                        let transaction = Transaction::change_by_selection(
                             doc.text(),
                             doc.selection(view_id),
                             |range| {
@@ -246,7 +464,8 @@ impl Completion {
 
                                 (cursor, cursor, Some(text.into()))
                             },
-                        )
+                        );
+                        (transaction, None)
                     }
                 }
             }
@@ -259,6 +478,7 @@ impl Completion {
             }
 
             let (view, doc) = current!(editor);
+            let completion_replace = editor.config().completion_replace;
 
             // if more text was entered, remove it
             doc.restore(view);
@@ -272,8 +492,14 @@ impl Completion {
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction =
-                        item_to_transaction(doc, view.id, item, start_offset, trigger_offset);
+                    let (transaction, _) = item_to_transaction(
+                        doc,
+                        view.id,
+                        item,
+                        start_offset,
+                        trigger_offset,
+                        completion_replace,
+                    );
 
                     // initialize a savepoint
                     doc.savepoint();
@@ -288,8 +514,14 @@ impl Completion {
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction =
-                        item_to_transaction(doc, view.id, item, start_offset, trigger_offset);
+                    let (transaction, snippet) = item_to_transaction(
+                        doc,
+                        view.id,
+                        item,
+                        start_offset,
+                        trigger_offset,
+                        completion_replace,
+                    );
 
                     apply_transaction(&transaction, doc, view);
 
@@ -298,6 +530,19 @@ impl Completion {
                         changes: completion_changes(&transaction, trigger_offset),
                     });
 
+                    // jump to the first tabstop, as a multi-selection so
+                    // mirrored occurrences of the same index are all
+                    // selected at once. There's no command to cycle to
+                    // later tabstops: by the time one could be invoked, this
+                    // popup (and the snippet state it would track) is gone.
+                    if let Some(ranges) = snippet.as_ref().map(ActiveSnippet::current_ranges) {
+                        let selection = Selection::new(
+                            ranges.iter().map(|&(s, e)| Range::new(s, e)).collect(),
+                            0,
+                        );
+                        doc.set_selection(view.id, selection);
+                    }
+
                     if let CompletionItem::LSP {
                         item,
                         offset_encoding,
@@ -343,6 +588,8 @@ impl Completion {
             popup,
             start_offset,
             trigger_offset,
+            path_preview_cache: None,
+            doc_scroll: 0,
         };
 
         // need to recompute immediately in case start_offset != trigger_offset
@@ -388,6 +635,10 @@ impl Completion {
     }
 
     pub fn recompute_filter(&mut self, editor: &Editor) {
+        // the selection is about to change (or disappear); start the next
+        // item's documentation scrolled back to the top
+        self.doc_scroll = 0;
+
         // recompute menu based on matches
         let menu = self.popup.contents_mut();
         let (view, doc) = current_ref!(editor);
@@ -430,6 +681,118 @@ impl Completion {
         self.popup.contents_mut().replace_option(old_item, new_item);
     }
 
+    /// Scrolls the documentation popup by `amount` lines (negative scrolls
+    /// up). The offset is re-clamped against the popup's actual content on
+    /// the next render, so overshooting here is harmless. Returns `false`
+    /// when there's no selected item (and so no documentation popup to
+    /// scroll), letting the keybinding fall through to its normal behavior.
+    pub fn scroll_docs(&mut self, amount: i16) -> bool {
+        if self.popup.contents().selection().is_none() {
+            return false;
+        }
+        self.doc_scroll = (self.doc_scroll as i16 + amount).max(0) as u16;
+        true
+    }
+
+    /// Returns the cached preview for `path`, computing (and caching) it
+    /// first if the selection has changed since the last render. `None`
+    /// means the path couldn't be read (e.g. permission denied), in which
+    /// case no preview popup should be shown.
+    fn path_preview(
+        &mut self,
+        path: &Path,
+        path_type: &PathType,
+        syn_loader: &helix_core::syntax::Loader,
+    ) -> Option<&PathPreview> {
+        if self.path_preview_cache.as_ref().map(|(cached, _)| cached.as_path()) != Some(path) {
+            let preview = Self::compute_path_preview(path, path_type, syn_loader);
+            self.path_preview_cache = Some((path.to_path_buf(), preview));
+        }
+        self.path_preview_cache.as_ref()?.1.as_ref()
+    }
+
+    /// Permission checks are deliberately left to `File::open`/`read_dir`
+    /// below via `.ok()?` rather than inspected up front: the owner/group/
+    /// other read bits alone don't tell us whether *this* process can read
+    /// the path (it may not own the file), and the bit that matters for a
+    /// directory is execute, not read anyway - actually attempting the
+    /// operation and degrading to "no preview" on failure covers both
+    /// correctly without a `Permissions` parameter at all.
+    fn compute_path_preview(
+        path: &Path,
+        path_type: &PathType,
+        syn_loader: &helix_core::syntax::Loader,
+    ) -> Option<PathPreview> {
+        match path_type {
+            PathType::File => {
+                use std::io::Read;
+                let mut file = std::fs::File::open(path).ok()?;
+                let mut buf = vec![0u8; PATH_PREVIEW_MAX_BYTES];
+                let n = file.read(&mut buf).ok()?;
+                buf.truncate(n);
+                let contents = String::from_utf8_lossy(&buf).into_owned();
+                let language = syn_loader
+                    .language_config_for_file_name(path)
+                    .map(|config| config.language_id.clone())
+                    .unwrap_or_default();
+                Some(PathPreview::File { language, contents })
+            }
+            PathType::Dir => {
+                let mut children: Vec<_> = std::fs::read_dir(path)
+                    .ok()?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        let path_type = PathType::of(&entry.path());
+                        (name, path_type)
+                    })
+                    .collect();
+                children.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Some(PathPreview::Dir { children })
+            }
+            PathType::Symlink => {
+                let target = std::fs::read_link(path).ok()?;
+                Some(PathPreview::Symlink { target })
+            }
+            PathType::Unknown => None,
+        }
+    }
+
+    /// Draws a scrollbar thumb along the documentation popup's right edge.
+    /// Only called when `content_height > area.height`, i.e. there's
+    /// something to scroll through.
+    fn render_doc_scrollbar(
+        &self,
+        area: Rect,
+        surface: &mut Surface,
+        cx: &Context,
+        content_height: u16,
+    ) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+        let max_scroll = content_height.saturating_sub(area.height);
+        let track = area.height as usize;
+        let thumb = ((track * area.height as usize) / content_height as usize).clamp(1, track);
+        let room = track.saturating_sub(thumb);
+        let thumb_start = if max_scroll == 0 {
+            0
+        } else {
+            (self.doc_scroll as usize * room) / max_scroll as usize
+        };
+
+        let x = area.x + area.width.saturating_sub(1);
+        let style = cx.editor.theme.get("ui.popup.scrollbar");
+        for y in 0..track {
+            let symbol = if y >= thumb_start && y < thumb_start + thumb {
+                "█"
+            } else {
+                "│"
+            };
+            surface.set_string(x, area.y + y as u16, symbol, style);
+        }
+    }
+
     /// Asynchronously requests that the currently selection completion item is
     /// resolved through LSP `completionItem/resolve`.
     pub fn ensure_item_resolved(&mut self, cx: &mut commands::Context) -> bool {
@@ -514,106 +877,199 @@ impl Component for Completion {
     fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
         self.popup.render(area, surface, cx);
 
-        // TODO show file contents for CompletionItem::Path
+        let docs_config = cx.editor.config().completion_documentation;
+        if !docs_config.enable {
+            return;
+        }
 
         // if we have a selection, render a markdown popup on top/below with info
-        if let Some(CompletionItem::LSP { item: option, .. }) = self.popup.contents().selection() {
-            // need to render:
-            // option.detail
-            // ---
-            // option.documentation
-
-            let (view, doc) = current!(cx.editor);
-            let language = doc.language_name().unwrap_or("");
-            let text = doc.text().slice(..);
-            let cursor_pos = doc.selection(view.id).primary().cursor(text);
-            let coords = helix_core::visual_coords_at_pos(text, cursor_pos, doc.tab_width());
-            let cursor_pos = (coords.row - view.offset.row) as u16;
-
-            let mut markdown_doc = match &option.documentation {
-                Some(lsp::Documentation::String(contents))
-                | Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
-                    kind: lsp::MarkupKind::PlainText,
-                    value: contents,
-                })) => {
-                    // TODO: convert to wrapped text
-                    Markdown::new(
-                        format!(
-                            "```{}\n{}\n```\n{}",
-                            language,
-                            option.detail.as_deref().unwrap_or_default(),
-                            contents.clone()
-                        ),
-                        cx.editor.syn_loader.clone(),
-                    )
-                }
-                Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
-                    kind: lsp::MarkupKind::Markdown,
-                    value: contents,
-                })) => {
-                    // TODO: set language based on doc scope
-                    Markdown::new(
-                        format!(
-                            "```{}\n{}\n```\n{}",
-                            language,
-                            option.detail.as_deref().unwrap_or_default(),
-                            contents.clone()
-                        ),
-                        cx.editor.syn_loader.clone(),
-                    )
-                }
-                None if option.detail.is_some() => {
-                    // TODO: copied from above
-
-                    // TODO: set language based on doc scope
-                    Markdown::new(
-                        format!(
-                            "```{}\n{}\n```",
-                            language,
-                            option.detail.as_deref().unwrap_or_default(),
-                        ),
-                        cx.editor.syn_loader.clone(),
-                    )
+        let selection = self.popup.contents().selection().cloned();
+        let Some(selection) = selection else {
+            return;
+        };
+
+        let (view, doc) = current!(cx.editor);
+        let language = doc.language_name().unwrap_or("");
+        let text = doc.text().slice(..);
+        let cursor_pos = doc.selection(view.id).primary().cursor(text);
+        let coords = helix_core::visual_coords_at_pos(text, cursor_pos, doc.tab_width());
+        let cursor_pos = (coords.row - view.offset.row) as u16;
+
+        let mut markdown_doc = match &selection {
+            CompletionItem::LSP { item: option, .. } => {
+                // need to render:
+                // option.detail
+                // ---
+                // option.documentation
+                match &option.documentation {
+                    Some(lsp::Documentation::String(contents))
+                    | Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                        kind: lsp::MarkupKind::PlainText,
+                        value: contents,
+                    })) => {
+                        // TODO: convert to wrapped text
+                        Markdown::new(
+                            format!(
+                                "```{}\n{}\n```\n{}",
+                                language,
+                                option.detail.as_deref().unwrap_or_default(),
+                                contents.clone()
+                            ),
+                            cx.editor.syn_loader.clone(),
+                        )
+                    }
+                    Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                        kind: lsp::MarkupKind::Markdown,
+                        value: contents,
+                    })) => {
+                        // TODO: set language based on doc scope
+                        Markdown::new(
+                            format!(
+                                "```{}\n{}\n```\n{}",
+                                language,
+                                option.detail.as_deref().unwrap_or_default(),
+                                contents.clone()
+                            ),
+                            cx.editor.syn_loader.clone(),
+                        )
+                    }
+                    None if option.detail.is_some() => {
+                        // TODO: copied from above
+
+                        // TODO: set language based on doc scope
+                        Markdown::new(
+                            format!(
+                                "```{}\n{}\n```",
+                                language,
+                                option.detail.as_deref().unwrap_or_default(),
+                            ),
+                            cx.editor.syn_loader.clone(),
+                        )
+                    }
+                    None => return,
                 }
-                None => return,
-            };
+            }
+            CompletionItem::Path { path, path_type, .. } => {
+                let syn_loader = cx.editor.syn_loader.clone();
+                let Some(preview) = self.path_preview(path, path_type, &syn_loader) else {
+                    return;
+                };
+                Markdown::new(
+                    preview.to_markdown(PATH_PREVIEW_MAX_LINES),
+                    cx.editor.syn_loader.clone(),
+                )
+            }
+        };
+
+        let (popup_x, popup_y) = self.popup.get_rel_position(area, cx);
+        let (popup_width, _popup_height) = self.popup.get_size();
+        let right_width = area
+            .width
+            .saturating_sub(popup_x)
+            .saturating_sub(popup_width);
+
+        let side = match docs_config.side {
+            CompletionDocsSide::Auto if right_width > MIN_SIDE_WIDTH => CompletionDocsSide::Right,
+            CompletionDocsSide::Auto => CompletionDocsSide::Below,
+            side => side,
+        };
 
-            let (popup_x, popup_y) = self.popup.get_rel_position(area, cx);
-            let (popup_width, _popup_height) = self.popup.get_size();
-            let mut width = area
-                .width
-                .saturating_sub(popup_x)
-                .saturating_sub(popup_width);
-            let area = if width > 30 {
-                let mut height = area.height.saturating_sub(popup_y);
+        let area = match side {
+            CompletionDocsSide::Right => {
+                let mut width = right_width.min(docs_config.max_width);
+                let mut height = area
+                    .height
+                    .saturating_sub(popup_y)
+                    .min(docs_config.max_height);
                 let x = popup_x + popup_width;
                 let y = popup_y;
 
-                if let Some((rel_width, rel_height)) = markdown_doc.required_size((width, height)) {
+                if let Some((rel_width, rel_height)) = markdown_doc.required_size((width, height))
+                {
                     width = rel_width.min(width);
                     height = rel_height.min(height);
                 }
                 Rect::new(x, y, width, height)
-            } else {
+            }
+            CompletionDocsSide::Left => {
+                let mut width = popup_x.min(docs_config.max_width);
+                let mut height = area
+                    .height
+                    .saturating_sub(popup_y)
+                    .min(docs_config.max_height);
+                let y = popup_y;
+
+                if let Some((rel_width, rel_height)) = markdown_doc.required_size((width, height))
+                {
+                    width = rel_width.min(width);
+                    height = rel_height.min(height);
+                }
+                let x = popup_x.saturating_sub(width);
+                Rect::new(x, y, width, height)
+            }
+            CompletionDocsSide::Below | CompletionDocsSide::Auto => {
                 let half = area.height / 2;
-                let height = 15.min(half);
+                let height = docs_config.max_height.min(half);
+                // Rows `area` reserves for the statusline/commandline that
+                // `tree.area()` (the actual editing surface) doesn't have,
+                // queried directly instead of assuming a fixed count - it
+                // varies with whichever chrome is configured on.
+                let reserved_rows = area.height.saturating_sub(cx.editor.tree.area().height);
                 // we want to make sure the cursor is visible (not hidden behind the documentation)
                 let y = if cursor_pos + area.y
-                    >= (cx.editor.tree.area().height - height - 2/* statusline + commandline */)
+                    >= cx
+                        .editor
+                        .tree
+                        .area()
+                        .height
+                        .saturating_sub(height)
+                        .saturating_sub(reserved_rows)
                 {
                     0
                 } else {
-                    // -2 to subtract command line + statusline. a bit of a hack, because of splits.
-                    area.height.saturating_sub(height).saturating_sub(2)
+                    area.height.saturating_sub(height).saturating_sub(reserved_rows)
                 };
 
                 Rect::new(0, y, area.width, height)
-            };
+            }
+        };
 
-            // clear area
-            let background = cx.editor.theme.get("ui.popup");
-            surface.clear_with(area, background);
+        // clear area
+        let background = cx.editor.theme.get("ui.popup");
+        surface.clear_with(area, background);
+
+        // the box above was sized to fit on screen, which may be smaller
+        // than the documentation itself; find out how much taller the full
+        // content is so we know how far we're allowed to scroll.
+        let content_height = markdown_doc
+            .required_size((area.width, u16::MAX))
+            .map_or(area.height, |(_, height)| height.max(area.height));
+        let max_scroll = content_height.saturating_sub(area.height);
+        self.doc_scroll = self.doc_scroll.min(max_scroll);
+
+        if self.doc_scroll == 0 && max_scroll == 0 {
             markdown_doc.render(area, surface, cx);
+        } else {
+            // render the full (unclipped) content to a scratch buffer, then
+            // copy just the visible window into `surface` at the scroll
+            // offset - `Markdown` itself has no concept of scrolling.
+            let content_area = Rect::new(area.x, area.y, area.width, content_height);
+            let mut scratch = Surface::empty(content_area);
+            scratch.set_style(content_area, background);
+            markdown_doc.render(content_area, &mut scratch, cx);
+
+            for y in 0..area.height {
+                let src_y = area.y + self.doc_scroll + y;
+                if src_y >= content_area.y + content_area.height {
+                    break;
+                }
+                for x in 0..area.width {
+                    *surface.get_mut(area.x + x, area.y + y) =
+                        scratch.get(area.x + x, src_y).clone();
+                }
+            }
+
+            self.render_doc_scrollbar(area, surface, cx, content_height);
         }
     }
 }